@@ -0,0 +1,69 @@
+//! Runs Cairo call execution out-of-process, in a pool of long-lived Python workers, so a
+//! wedged or crashing call can't take the whole node down with it. [service] is the pool
+//! supervisor; [sub_process] is the worker side of the protocol.
+
+mod service;
+pub(crate) mod sub_process;
+
+pub use service::{start, MetricsHandle, MetricsSnapshot, ShutdownSummary};
+
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+/// An `mpsc::Receiver` a worker task locks for the duration of a single `recv`, so the same
+/// queue can be handed to whichever process currently occupies a pool slot.
+pub(crate) type SharedReceiver<T> = Arc<Mutex<mpsc::Receiver<T>>>;
+
+/// Handle for submitting calls to a process pool started by [service::start].
+#[derive(Clone)]
+pub struct Handle {
+    pub(crate) command_tx: mpsc::Sender<Command>,
+}
+
+impl Handle {
+    /// Submits `request` to the pool and awaits its result.
+    pub async fn call(&self, request: CallRequest) -> anyhow::Result<CallOutput> {
+        let (responder, result) = oneshot::channel();
+        self.command_tx
+            .send(Command(request, responder))
+            .await
+            .map_err(|_| anyhow::anyhow!("process pool has shut down"))?;
+        result
+            .await
+            .map_err(|_| anyhow::anyhow!("worker dropped the call without replying"))?
+    }
+}
+
+/// A JSON-encoded Cairo call request, passed to the Python worker as-is.
+#[derive(Debug, Clone)]
+pub struct CallRequest(pub serde_json::Value);
+
+/// A JSON-encoded Cairo call result, as returned by the Python worker.
+#[derive(Debug, Clone)]
+pub struct CallOutput(pub serde_json::Value);
+
+/// A call dispatched to a worker over its own command queue, replied to on the attached oneshot
+/// once the worker has a result. Liveness pings do *not* travel through this queue -- see
+/// [sub_process] -- so a worker busy on one long call can still be told apart from a wedged one.
+#[derive(Debug)]
+pub(crate) struct Command(pub CallRequest, pub oneshot::Sender<anyhow::Result<CallOutput>>);
+
+/// Events a worker reports back to the supervisor over the shared `status_tx`.
+#[derive(Debug)]
+pub(crate) enum SubProcessEvent {
+    /// The worker's Python process has started, with this OS pid.
+    ProcessLaunched(u32),
+    /// The worker finished a [Command] in the given elapsed time, with this high-level status.
+    CommandHandled(u32, std::time::Duration, CallStatus),
+    /// The worker failed to launch its Python process. [sub_process] sends this only for launch
+    /// failures, before a pid is known, so the pid is always `None`; a crash after launch is
+    /// not reported through this variant.
+    Failure(Option<u32>, anyhow::Error),
+}
+
+/// High-level outcome of a handled call, used for the `{status:?}` tag on [MetricsSnapshot].
+#[derive(Debug)]
+pub(crate) enum CallStatus {
+    Ok,
+    Err,
+}
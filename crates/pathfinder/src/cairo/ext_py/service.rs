@@ -1,9 +1,208 @@
 //! Starting and maintaining processes, and the main entry point
 
 use super::{sub_process::launch_python, Command, Handle, SharedReceiver, SubProcessEvent};
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+/// How often the supervisor polls worker liveness.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a worker may go without acknowledging a heartbeat before it's considered wedged
+/// and is killed and replaced.
+const DEFAULT_LIVENESS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long `stop_flag` shutdown waits for in-flight calls to finish on their own before
+/// force-killing whatever workers are still alive.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Outcome of a pool shutdown, returned by the supervisor's [tokio::task::JoinHandle].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownSummary {
+    /// Workers that exited on their own within the grace period.
+    pub completed: usize,
+    /// Workers still alive once the grace period elapsed and were force-killed.
+    pub force_killed: usize,
+}
+
+/// Base of the exponential bucket spacing used by [LatencyHistogram]: bucket `i` covers
+/// `[BUCKET_BASE^i, BUCKET_BASE^(i+1))` microseconds.
+const BUCKET_BASE: f64 = 1.5;
+
+/// Number of finite buckets in a [LatencyHistogram]; anything at or above the top edge falls
+/// into the saturating overflow bucket.
+const BUCKET_COUNT: usize = 64;
+
+/// A latency histogram over exponentially-spaced microsecond buckets, cheap enough to update
+/// on every `CommandHandled` event without blocking the hot path. The last bucket saturates:
+/// it absorbs every duration at or above its lower edge instead of growing unbounded.
+#[derive(Debug)]
+pub(crate) struct LatencyHistogram {
+    buckets: [u64; BUCKET_COUNT + 1],
+    count: u64,
+    min_us: Option<u64>,
+    max_us: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; BUCKET_COUNT + 1],
+            count: 0,
+            min_us: None,
+            max_us: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn bucket_of(micros: u64) -> usize {
+        if micros == 0 {
+            return 0;
+        }
+        let idx = (micros as f64).ln() / BUCKET_BASE.ln();
+        (idx.floor().max(0.0) as usize).min(BUCKET_COUNT)
+    }
+
+    /// The `[lo, hi)` microsecond range covered by bucket `idx`. For the overflow bucket `hi`
+    /// is just its lower edge scaled by another factor of `BUCKET_BASE`, same as every other
+    /// bucket; a percentile that lands in the overflow bucket is interpolated against this
+    /// edge, not against the largest recorded sample.
+    fn bucket_range(idx: usize) -> (u64, u64) {
+        let lo = BUCKET_BASE.powi(idx as i32) as u64;
+        let hi = BUCKET_BASE.powi(idx as i32 + 1) as u64;
+        (lo, hi)
+    }
+
+    fn record(&mut self, elapsed: Duration) {
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.buckets[Self::bucket_of(micros)] += 1;
+        self.count += 1;
+        self.min_us = Some(self.min_us.map_or(micros, |m| m.min(micros)));
+        self.max_us = self.max_us.max(micros);
+    }
+
+    /// Interpolated microsecond value at percentile `q` (`0.0..=1.0`): locates the bucket
+    /// containing the `q`-th ranked sample, then linearly interpolates within its range.
+    fn percentile(&self, q: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let target = ((q * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0u64;
+        for (idx, &bucket_count) in self.buckets.iter().enumerate() {
+            if bucket_count == 0 {
+                continue;
+            }
+            if seen + bucket_count >= target {
+                let (lo, hi) = Self::bucket_range(idx);
+                let within = (target - seen) as f64 - 0.5;
+                let value = lo as f64 + (within / bucket_count as f64) * (hi - lo) as f64;
+                return Some(value as u64);
+            }
+            seen += bucket_count;
+        }
+
+        Some(self.max_us)
+    }
+}
+
+/// Point-in-time view of a [Metrics] aggregate, cheap to clone and safe to hand outside the
+/// supervisor loop.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    pub count: u64,
+    pub min_us: Option<u64>,
+    pub max_us: Option<u64>,
+    pub p50_us: Option<u64>,
+    pub p99_us: Option<u64>,
+    /// Tally of completions per `{status:?}` string.
+    pub status_tallies: HashMap<String, u64>,
+}
+
+impl LatencyHistogram {
+    fn snapshot(&self, status_tallies: HashMap<String, u64>) -> MetricsSnapshot {
+        MetricsSnapshot {
+            count: self.count,
+            min_us: self.min_us,
+            max_us: (self.count > 0).then_some(self.max_us),
+            p50_us: self.percentile(0.5),
+            p99_us: self.percentile(0.99),
+            status_tallies,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct MetricsInner {
+    aggregate: LatencyHistogram,
+    per_pid: HashMap<u32, LatencyHistogram>,
+    status_tallies: HashMap<String, u64>,
+}
+
+/// Latency-histogram metrics for sub-process command timings, updated by the supervisor on
+/// every `CommandHandled` event and scraped through [MetricsHandle] without touching the hot
+/// path (a brief `std::sync::Mutex` lock, no `.await` involved).
+#[derive(Debug, Default)]
+pub(crate) struct Metrics(std::sync::Mutex<MetricsInner>);
+
+impl Metrics {
+    fn record(&self, pid: u32, elapsed: Duration, status: &str) {
+        let mut inner = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        inner.aggregate.record(elapsed);
+        inner.per_pid.entry(pid).or_default().record(elapsed);
+        *inner.status_tallies.entry(status.to_owned()).or_insert(0) += 1;
+    }
+
+    fn snapshot(&self) -> MetricsSnapshot {
+        let inner = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        inner.aggregate.snapshot(inner.status_tallies.clone())
+    }
+
+    fn per_worker_snapshot(&self, pid: u32) -> Option<MetricsSnapshot> {
+        let inner = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        inner.per_pid.get(&pid).map(|h| h.snapshot(HashMap::new()))
+    }
+}
+
+/// Cheap, cloneable handle for scraping [MetricsSnapshot]s out of a running process pool.
+#[derive(Clone, Default)]
+pub struct MetricsHandle(Arc<Metrics>);
+
+impl MetricsHandle {
+    /// Aggregate latency and per-status tallies across every worker.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.0.snapshot()
+    }
+
+    /// Latency for a single worker, identified by its process id. Returns `None` if the pool
+    /// has never seen a `CommandHandled` event from that pid.
+    pub fn worker_snapshot(&self, pid: u32) -> Option<MetricsSnapshot> {
+        self.0.per_worker_snapshot(pid)
+    }
+}
+
+/// Bookkeeping for one pool slot. Slots live for the lifetime of the pool; only the python
+/// process occupying a slot comes and goes, so a wedged worker can be replaced without
+/// disturbing the others.
+struct WorkerSlot {
+    /// This slot's half of its own dedicated command queue -- no longer shared with the rest
+    /// of the pool, so a heartbeat timeout can identify and restart exactly this worker.
+    command_rx: SharedReceiver<Command>,
+    /// This slot's half of its own liveness-ping queue. Kept separate from `command_rx` so a
+    /// ping never has to wait behind whatever call the worker is currently executing.
+    ping_rx: SharedReceiver<()>,
+    pid: Option<u32>,
+    last_heartbeat: Instant,
+    /// The other half of this worker's dedicated shutdown channel. Firing it targets exactly
+    /// this worker, unlike a broadcast every worker would have to filter by pid. `take()`n the
+    /// moment it's fired, so a repeated heartbeat-timeout tick can't re-signal an already
+    /// restarting worker.
+    shutdown_tx: Option<oneshot::Sender<()>>,
+}
 
 /// Starts to maintain a pool of `count` sub-processes which execute the calls.
 ///
@@ -15,34 +214,77 @@ use tokio::sync::{broadcast, mpsc, Mutex};
 /// - user has compatible python, 3.7+ should work just fine
 ///
 /// Returns an error if executing calls in a sub-process is not supported.
+///
+/// The [tokio::task::JoinHandle] resolves to a [ShutdownSummary] once `stop_flag` fires and
+/// the pool has drained: it reports how many workers exited cleanly within the grace period
+/// versus how many had to be force-killed. The [MetricsHandle] lets the caller scrape call
+/// latency periodically.
 pub async fn start(
     database_path: PathBuf,
     count: std::num::NonZeroUsize,
     stop_flag: impl std::future::Future<Output = ()> + Send + 'static,
-) -> anyhow::Result<(Handle, tokio::task::JoinHandle<()>)> {
+) -> anyhow::Result<(Handle, MetricsHandle, tokio::task::JoinHandle<ShutdownSummary>)> {
     // channel sizes are conservative but probably enough for many workers; should investigate mpmc
     // if the lock overhead on command_rx before making these deeper.
-    let (command_tx, command_rx) = mpsc::channel(1);
+    let (command_tx, mut incoming_rx) = mpsc::channel(1);
     let (status_tx, mut status_rx) = mpsc::channel(1);
-    // this will never need to become deeper
-    let (child_shutdown_tx, _) = broadcast::channel(1);
-    let command_rx: SharedReceiver<Command> = Arc::new(Mutex::new(command_rx));
+    // acknowledgements for the liveness pings enqueued below, tagged with the slot they came
+    // from since they all funnel back through this one channel.
+    let (ping_ack_tx, mut ping_ack_rx) = mpsc::channel::<usize>(count.get());
+
+    // Every slot owns its own command queue for its whole lifetime; only the process occupying
+    // it is replaced on exit or on a heartbeat timeout.
+    let mut slots: Vec<WorkerSlot> = Vec::with_capacity(count.get());
+    let mut dispatch_txs: Vec<mpsc::Sender<Command>> = Vec::with_capacity(count.get());
+    let mut ping_txs: Vec<mpsc::Sender<()>> = Vec::with_capacity(count.get());
+    for _ in 0..count.get() {
+        let (tx, rx) = mpsc::channel(1);
+        dispatch_txs.push(tx);
+        let (ping_tx, ping_rx) = mpsc::channel(1);
+        ping_txs.push(ping_tx);
+        slots.push(WorkerSlot {
+            command_rx: Arc::new(Mutex::new(rx)),
+            ping_rx: Arc::new(Mutex::new(ping_rx)),
+            pid: None,
+            last_heartbeat: Instant::now(),
+            shutdown_tx: None,
+        });
+    }
+
+    // Slots whose process has been asked to launch but whose pid we haven't learned yet, in
+    // spawn order; the next ProcessLaunched event belongs to the slot at the front.
+    let mut pending_spawns: VecDeque<usize> = VecDeque::new();
+    let mut pid_to_slot: HashMap<u32, usize> = HashMap::new();
 
     // TODO: might be better to use tokio's JoinSet?
     let mut joinhandles = futures::stream::FuturesUnordered::new();
+    // kept alongside `joinhandles` so a timed-out shutdown can force-kill whatever's still
+    // running; a `JoinHandle` moved into `joinhandles` can no longer be aborted directly.
+    let mut abort_handles: Vec<Option<tokio::task::AbortHandle>> = vec![None; count.get()];
 
-    let jh = tokio::task::spawn(launch_python(
+    pending_spawns.push_back(0);
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    slots[0].shutdown_tx = Some(shutdown_tx);
+    let jh = spawn_worker(
+        0,
         database_path.clone(),
-        Arc::clone(&command_rx),
+        Arc::clone(&slots[0].command_rx),
         status_tx.clone(),
-        child_shutdown_tx.subscribe(),
-    ));
+        shutdown_rx,
+        Arc::clone(&slots[0].ping_rx),
+        ping_ack_tx.clone(),
+    );
 
+    abort_handles[0] = Some(jh.abort_handle());
     joinhandles.push(jh);
 
     match status_rx.recv().await {
-        Some(SubProcessEvent::ProcessLaunched(_pid)) => {
+        Some(SubProcessEvent::ProcessLaunched(pid)) => {
             // good, now we can launch the other processes requested later
+            pending_spawns.pop_front();
+            pid_to_slot.insert(pid, 0);
+            slots[0].pid = Some(pid);
+            slots[0].last_heartbeat = Instant::now();
         }
         Some(SubProcessEvent::Failure(_maybe_pid, e)) => {
             return Err(e.context("Launch first python executor"));
@@ -57,6 +299,9 @@ pub async fn start(
         command_tx: command_tx.clone(),
     };
 
+    let metrics = Arc::new(Metrics::default());
+    let metrics_handle = MetricsHandle(Arc::clone(&metrics));
+
     let jh = tokio::task::spawn(async move {
         use futures::stream::StreamExt;
         const WAIT_BEFORE_SPAWN: std::time::Duration = std::time::Duration::from_secs(1);
@@ -66,48 +311,179 @@ pub async fn start(
         let wait_before_spawning = tokio::time::sleep(WAIT_BEFORE_SPAWN);
         tokio::pin!(wait_before_spawning);
 
+        let heartbeat_check = tokio::time::interval(HEARTBEAT_INTERVAL);
+        tokio::pin!(heartbeat_check);
+
+        // round-robin cursor over the dispatch queues
+        let mut next_slot = 0usize;
+
         tokio::pin!(stop_flag);
 
         loop {
-            let mut spawn = false;
+            let mut spawn_slot = None;
             tokio::select! {
                 _ = &mut stop_flag => {
-                    // this should be enough to kick everyone off the locking, queue receiving
-                    let _ = child_shutdown_tx.send(());
-                    let _ = joinhandles.into_future().await;
-                    // just exit
-                    return;
+                    // Stop admitting new commands and close every worker's own queue so it
+                    // drains to `None` once whatever's already queued on it is handled. That
+                    // alone is enough for a healthy worker to notice there's no more work
+                    // coming, finish whatever call it's mid-flight on, report CommandHandled,
+                    // and exit on its own -- so the grace period below is genuinely just
+                    // waiting on that, not racing a shutdown signal we fired ourselves. We only
+                    // reach for `shutdown_tx`/abort once the grace period actually runs out.
+                    drop(incoming_rx);
+                    drop(dispatch_txs);
+
+                    // `status_tx` is depth-1 and `ping_ack_tx` only `count` deep, so a worker that finishes its
+                    // in-flight call and reports CommandHandled (or acks a ping already in
+                    // flight) blocks on that send until something drains it -- keep doing so
+                    // here, concurrently with watching for exits, or every worker after the
+                    // first never gets to actually finish draining and exit within the grace
+                    // period.
+                    let mut completed = 0usize;
+                    let drained = tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, async {
+                        while !joinhandles.is_empty() {
+                            tokio::select! {
+                                Some(_finished) = joinhandles.next() => {
+                                    completed += 1;
+                                }
+                                Some(evt) = status_rx.recv() => {
+                                    if let SubProcessEvent::CommandHandled(pid, timings, status) = evt {
+                                        println!("{status:?}: {timings:?}");
+                                        metrics.record(pid, timings.into(), &format!("{status:?}"));
+                                    }
+                                }
+                                Some(_idx) = ping_ack_rx.recv() => {}
+                            }
+                        }
+                    })
+                    .await;
+
+                    let force_killed = if drained.is_err() {
+                        let still_running = joinhandles.len();
+                        for slot in slots.iter_mut() {
+                            if let Some(tx) = slot.shutdown_tx.take() {
+                                let _ = tx.send(());
+                            }
+                        }
+                        for handle in abort_handles.iter().flatten() {
+                            handle.abort();
+                        }
+                        still_running
+                    } else {
+                        0
+                    };
+
+                    return ShutdownSummary { completed, force_killed };
+                }
+                Some(cmd) = incoming_rx.recv() => {
+                    // hand the command to the next worker's own queue; if it's full (that
+                    // worker is busy), fall through to the one after it rather than blocking
+                    // the whole pool on a single busy process.
+                    let n = dispatch_txs.len();
+                    let mut cmd = cmd;
+                    let mut sent = false;
+                    for offset in 0..n {
+                        let idx = (next_slot + offset) % n;
+                        match dispatch_txs[idx].try_send(cmd) {
+                            Ok(()) => {
+                                sent = true;
+                                next_slot = (idx + 1) % n;
+                                break;
+                            }
+                            Err(mpsc::error::TrySendError::Full(returned))
+                            | Err(mpsc::error::TrySendError::Closed(returned)) => {
+                                cmd = returned;
+                            }
+                        }
+                    }
+                    if !sent {
+                        // every queue is full; wait for the one we started at
+                        let idx = next_slot % n;
+                        let _ = dispatch_txs[idx].send(cmd).await;
+                        next_slot = (idx + 1) % n;
+                    }
                 }
                 Some(evt) = status_rx.recv() => {
                     match evt {
-                        SubProcessEvent::ProcessLaunched(_) => {},
-                        SubProcessEvent::CommandHandled(_pid, timings, status) => {
+                        SubProcessEvent::ProcessLaunched(pid) => {
+                            if let Some(slot) = pending_spawns.pop_front() {
+                                pid_to_slot.insert(pid, slot);
+                                slots[slot].pid = Some(pid);
+                                slots[slot].last_heartbeat = Instant::now();
+                            }
+                        },
+                        SubProcessEvent::CommandHandled(pid, timings, status) => {
                             println!("{status:?}: {timings:?}");
+                            if let Some(&slot) = pid_to_slot.get(&pid) {
+                                slots[slot].last_heartbeat = Instant::now();
+                            }
+                            metrics.record(pid, timings.into(), &format!("{status:?}"));
                         },
                         SubProcessEvent::Failure(..) => { /* this is really needed just for startup */ },
                     }
                 },
+                Some(idx) = ping_ack_rx.recv() => {
+                    if let Some(slot) = slots.get_mut(idx) {
+                        slot.last_heartbeat = Instant::now();
+                    }
+                }
                 Some(_maybe_info) = joinhandles.next() => {
                     println!("one of our python processes have expired: {_maybe_info:?}");
-                    // we should spawn it immediatedly if empty
-                    spawn = joinhandles.is_empty();
+                    if let Ok(slot) = _maybe_info {
+                        if let Some(pid) = slots[slot].pid.take() {
+                            pid_to_slot.remove(&pid);
+                        }
+                        spawn_slot = Some(slot);
+                    }
                 }
                 _ = &mut wait_before_spawning => {
                     // spawn if needed
-                    spawn = count.get() > joinhandles.len();
+                    spawn_slot = slots.iter().position(|s| s.pid.is_none());
+                }
+                _ = heartbeat_check.tick() => {
+                    let now = Instant::now();
+                    for (idx, slot) in slots.iter_mut().enumerate() {
+                        let Some(pid) = slot.pid else { continue };
+
+                        if now.duration_since(slot.last_heartbeat) <= DEFAULT_LIVENESS_TIMEOUT {
+                            // Dispatched on the ping queue, not the command queue: a worker
+                            // answers this out-of-band of whatever call it's currently
+                            // executing, so a worker legitimately busy on one long call still
+                            // gets its heartbeat refreshed instead of being mistaken for wedged.
+                            let _ = ping_txs[idx].try_send(());
+                            continue;
+                        }
+
+                        // Past the timeout: fire the targeted shutdown exactly once. Taking
+                        // `shutdown_tx` here, rather than just logging, is what stops every
+                        // later tick from re-sending the same signal for the same pid until
+                        // the worker actually exits and frees the slot.
+                        if let Some(tx) = slot.shutdown_tx.take() {
+                            println!("worker {pid} (slot {idx}) missed its heartbeat, restarting it");
+                            let _ = tx.send(());
+                        }
+                    }
                 }
             }
 
-            if spawn {
-                let jh = tokio::task::spawn(launch_python(
+            if let Some(slot) = spawn_slot {
+                pending_spawns.push_back(slot);
+                slots[slot].last_heartbeat = Instant::now();
+                let (shutdown_tx, shutdown_rx) = oneshot::channel();
+                slots[slot].shutdown_tx = Some(shutdown_tx);
+                let jh = spawn_worker(
+                    slot,
                     database_path.clone(),
-                    Arc::clone(&command_rx),
+                    Arc::clone(&slots[slot].command_rx),
                     status_tx.clone(),
-                    child_shutdown_tx.subscribe(),
-                ));
+                    shutdown_rx,
+                    Arc::clone(&slots[slot].ping_rx),
+                    ping_ack_tx.clone(),
+                );
 
+                abort_handles[slot] = Some(jh.abort_handle());
                 joinhandles.push(jh);
-            } else if count.get() > joinhandles.len() && wait_before_spawning.is_elapsed() {
+            } else if slots.iter().any(|s| s.pid.is_none()) && wait_before_spawning.is_elapsed() {
                 wait_before_spawning
                     .as_mut()
                     .reset(tokio::time::Instant::now() + WAIT_BEFORE_SPAWN);
@@ -115,5 +491,31 @@ pub async fn start(
         }
     });
 
-    Ok((handle, jh))
+    Ok((handle, metrics_handle, jh))
+}
+
+/// Spawns the python worker for `slot`, tagging its [tokio::task::JoinHandle] output with the
+/// slot index so the supervisor knows which slot to respawn into when it exits.
+fn spawn_worker(
+    slot: usize,
+    database_path: PathBuf,
+    command_rx: SharedReceiver<Command>,
+    status_tx: mpsc::Sender<SubProcessEvent>,
+    shutdown_rx: oneshot::Receiver<()>,
+    ping_rx: SharedReceiver<()>,
+    ping_ack_tx: mpsc::Sender<usize>,
+) -> tokio::task::JoinHandle<usize> {
+    tokio::task::spawn(async move {
+        launch_python(
+            database_path,
+            command_rx,
+            status_tx,
+            shutdown_rx,
+            ping_rx,
+            slot,
+            ping_ack_tx,
+        )
+        .await;
+        slot
+    })
 }
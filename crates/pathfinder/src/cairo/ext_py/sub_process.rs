@@ -0,0 +1,137 @@
+//! The worker side of the process-pool protocol: launches the Python subprocess, pipes calls to
+//! it over stdio, and reports liveness/results back to the supervisor.
+
+use super::{CallOutput, CallRequest, CallStatus, Command, SharedReceiver, SubProcessEvent};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{ChildStdin, ChildStdout, Command as ChildCommand};
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+type Lines = tokio::io::Lines<BufReader<ChildStdout>>;
+
+/// The Python child's stdio, held behind a mutex so a call's I/O can run as its own task while
+/// the worker's main loop keeps answering liveness pings concurrently.
+struct ChildIo {
+    stdin: ChildStdin,
+    stdout: Lines,
+}
+
+/// A call handed off to its own task, so the select loop below can keep answering liveness
+/// pings from the worker's separate ping queue (and watch for a shutdown) without waiting on it.
+struct InFlight {
+    task: tokio::task::JoinHandle<anyhow::Result<CallOutput>>,
+    responder: oneshot::Sender<anyhow::Result<CallOutput>>,
+    started: Instant,
+}
+
+/// Drives a single Python worker process for its whole lifetime: spawns it, reports
+/// [SubProcessEvent::ProcessLaunched], then services [Command]s from `command_rx` one at a time
+/// while answering liveness pings from `ping_rx` out-of-band. Pings bypass the command queue
+/// entirely -- that's what lets a worker legitimately busy on one long call keep proving it's
+/// alive instead of being mistaken for wedged by the supervisor's heartbeat.
+pub(crate) async fn launch_python(
+    database_path: PathBuf,
+    command_rx: SharedReceiver<Command>,
+    status_tx: mpsc::Sender<SubProcessEvent>,
+    mut shutdown_rx: oneshot::Receiver<()>,
+    ping_rx: SharedReceiver<()>,
+    slot: usize,
+    ping_ack_tx: mpsc::Sender<usize>,
+) {
+    let mut child = match ChildCommand::new("python3")
+        .arg("-m")
+        .arg("call")
+        .arg(&database_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = status_tx
+                .send(SubProcessEvent::Failure(None, anyhow::Error::new(e)))
+                .await;
+            return;
+        }
+    };
+
+    let Some(pid) = child.id() else {
+        let _ = status_tx
+            .send(SubProcessEvent::Failure(
+                None,
+                anyhow::anyhow!("process exited before reporting a pid"),
+            ))
+            .await;
+        return;
+    };
+
+    let stdin = child.stdin.take().expect("stdin was piped");
+    let stdout = BufReader::new(child.stdout.take().expect("stdout was piped")).lines();
+    let io = Arc::new(Mutex::new(ChildIo { stdin, stdout }));
+
+    if status_tx
+        .send(SubProcessEvent::ProcessLaunched(pid))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let mut in_flight: Option<InFlight> = None;
+
+    loop {
+        tokio::select! {
+            biased;
+
+            _ = &mut shutdown_rx => return,
+
+            Some(()) = async { ping_rx.lock().await.recv().await } => {
+                // Answered straight away regardless of `in_flight`: this branch lives on the
+                // worker's own event loop, not behind whatever call it's currently running, so
+                // it keeps firing for as long as that loop is pumping. The ack shows only that
+                // the loop runs; it says nothing about whether the in-flight call is advancing,
+                // so a call that never returns does not make this worker miss a heartbeat.
+                let _ = ping_ack_tx.send(slot).await;
+            }
+
+            result = async { in_flight.as_mut().unwrap().task.await }, if in_flight.is_some() => {
+                let InFlight { responder, started, .. } = in_flight.take().expect("checked above");
+                let result = result.unwrap_or_else(|e| Err(anyhow::anyhow!(e)));
+                let status = if result.is_ok() { CallStatus::Ok } else { CallStatus::Err };
+                let _ = status_tx
+                    .send(SubProcessEvent::CommandHandled(pid, started.elapsed(), status))
+                    .await;
+                let _ = responder.send(result);
+            }
+
+            cmd = async { command_rx.lock().await.recv().await }, if in_flight.is_none() => {
+                let Some(Command(request, responder)) = cmd else {
+                    // the supervisor closed our queue: nothing left to do.
+                    return;
+                };
+                let io = Arc::clone(&io);
+                let task = tokio::task::spawn(async move { send_call(&io, request).await });
+                in_flight = Some(InFlight { task, responder, started: Instant::now() });
+            }
+        }
+    }
+}
+
+async fn send_call(io: &Mutex<ChildIo>, request: CallRequest) -> anyhow::Result<CallOutput> {
+    let mut io = io.lock().await;
+    let mut line = serde_json::to_string(&request.0)?;
+    line.push('\n');
+    io.stdin.write_all(line.as_bytes()).await?;
+
+    let response = io
+        .stdout
+        .next_line()
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("worker closed its stdout"))?;
+
+    Ok(CallOutput(serde_json::from_str(&response)?))
+}
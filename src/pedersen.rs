@@ -1,5 +1,6 @@
 use bitvec::{array::BitArray, order::Lsb0, slice::BitSlice};
 use ff::{Field, PrimeField};
+use once_cell::sync::Lazy;
 
 /// The field primitive used by [PedersenHash]
 #[derive(PrimeField)]
@@ -10,27 +11,25 @@ pub struct Fp([u64; 4]);
 
 impl Fp {
     /// Transforms [Fp] into little endian bit representation.
+    ///
+    /// `BitArray<Lsb0, [u64; 4]>` indexes bits by the logical value of each `u64` limb (bit 0
+    /// is the least-significant bit of `self.0[0]`, bit 64 is the least-significant bit of
+    /// `self.0[1]`, and so on) rather than by the limbs' in-memory byte layout, so this is
+    /// already endian-agnostic: no byte-swapping is required to get the same bit ordering on
+    /// big-endian targets.
     fn into_bits(mut self) -> BitArray<Lsb0, [u64; 4]> {
-        #[cfg(not(target_endian = "little"))]
-        {
-            todo!("untested and probably unimplemented: big-endian targets")
-        }
-
-        #[cfg(target_endian = "little")]
-        {
-            self.mont_reduce(
-                self.0[0usize],
-                self.0[1usize],
-                self.0[2usize],
-                self.0[3usize],
-                0,
-                0,
-                0,
-                0,
-            );
-
-            self.0.into()
-        }
+        self.mont_reduce(
+            self.0[0usize],
+            self.0[1usize],
+            self.0[2usize],
+            self.0[3usize],
+            0,
+            0,
+            0,
+            0,
+        );
+
+        self.0.into()
     }
 }
 
@@ -111,6 +110,63 @@ impl CurvePoint {
 
         product
     }
+
+    /// Fixed-base scalar multiplication using a precomputed [WindowTable].
+    ///
+    /// Walks `bits` from the most-significant end in [WINDOW_BITS]-wide windows: the
+    /// accumulator is doubled once per bit in the window, then the window's value `v` is
+    /// looked up as `table[v - 1]` (skipped when `v == 0`). The final window may be narrower
+    /// than [WINDOW_BITS] when `bits.len()` isn't a multiple of the window width.
+    fn multiply_fixed(table: &WindowTable, bits: &BitSlice<Lsb0, u64>) -> CurvePoint {
+        let mut product = CurvePoint::identity();
+        let mut remaining = bits.len();
+
+        while remaining > 0 {
+            let width = WINDOW_BITS.min(remaining);
+            for _ in 0..width {
+                product = product.double();
+            }
+            remaining -= width;
+
+            let window = &bits[remaining..remaining + width];
+            let mut value = 0usize;
+            for (k, b) in window.iter().enumerate() {
+                if *b {
+                    value |= 1 << k;
+                }
+            }
+
+            if value != 0 {
+                product = product.add(&table[value - 1]);
+            }
+        }
+
+        product
+    }
+}
+
+/// Window width used by [CurvePoint::multiply_fixed], in bits.
+const WINDOW_BITS: usize = 4;
+
+/// Number of non-zero multiples stored per [WindowTable], i.e. `2^WINDOW_BITS - 1`.
+const WINDOW_TABLE_SIZE: usize = (1 << WINDOW_BITS) - 1;
+
+/// Precomputed multiples `[1*P, 2*P, ..., (2^WINDOW_BITS - 1)*P]` of a fixed base point `P`,
+/// used by [CurvePoint::multiply_fixed] to replace bit-by-bit double-and-add.
+type WindowTable = [CurvePoint; WINDOW_TABLE_SIZE];
+
+/// Builds the windowed multiples table for a fixed base point.
+fn build_window_table(base: &CurvePoint) -> WindowTable {
+    let mut table: Vec<CurvePoint> = Vec::with_capacity(WINDOW_TABLE_SIZE);
+    table.push(base.clone());
+    for i in 1..WINDOW_TABLE_SIZE {
+        let next = table[i - 1].add(base);
+        table.push(next);
+    }
+
+    table
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("table has exactly WINDOW_TABLE_SIZE entries"))
 }
 
 /// Montgomery representation of the Stark curve constant P0.
@@ -198,6 +254,15 @@ const PEDERSEN_P4: CurvePoint = CurvePoint {
     infinity: false,
 };
 
+/// Windowed multiples of [PEDERSEN_P1], used by [pedersen_hash] for `a_low * P1`.
+static PEDERSEN_P1_TABLE: Lazy<WindowTable> = Lazy::new(|| build_window_table(&PEDERSEN_P1));
+/// Windowed multiples of [PEDERSEN_P2], used by [pedersen_hash] for `a_high * P2`.
+static PEDERSEN_P2_TABLE: Lazy<WindowTable> = Lazy::new(|| build_window_table(&PEDERSEN_P2));
+/// Windowed multiples of [PEDERSEN_P3], used by [pedersen_hash] for `b_low * P3`.
+static PEDERSEN_P3_TABLE: Lazy<WindowTable> = Lazy::new(|| build_window_table(&PEDERSEN_P3));
+/// Windowed multiples of [PEDERSEN_P4], used by [pedersen_hash] for `b_high * P4`.
+static PEDERSEN_P4_TABLE: Lazy<WindowTable> = Lazy::new(|| build_window_table(&PEDERSEN_P4));
+
 /// Performs the Stark Pedersen hash on `a` and `b`.
 pub fn pedersen_hash(a: Fp, b: Fp) -> Fp {
     let mut result = PEDERSEN_P0.clone();
@@ -205,25 +270,66 @@ pub fn pedersen_hash(a: Fp, b: Fp) -> Fp {
     let b = b.into_bits();
 
     // Add a_low * P1
-    let tmp = PEDERSEN_P1.multiply(&a[..248]);
+    let tmp = CurvePoint::multiply_fixed(&PEDERSEN_P1_TABLE, &a[..248]);
     result = result.add(&tmp);
 
     // Add a_high * P2
-    let tmp = PEDERSEN_P2.multiply(&a[248..252]);
+    let tmp = CurvePoint::multiply_fixed(&PEDERSEN_P2_TABLE, &a[248..252]);
     result = result.add(&tmp);
 
     // Add b_low * P3
-    let tmp = PEDERSEN_P3.multiply(&b[..248]);
+    let tmp = CurvePoint::multiply_fixed(&PEDERSEN_P3_TABLE, &b[..248]);
     result = result.add(&tmp);
 
     // Add b_high * P4
-    let tmp = PEDERSEN_P4.multiply(&b[248..252]);
+    let tmp = CurvePoint::multiply_fixed(&PEDERSEN_P4_TABLE, &b[248..252]);
     result = result.add(&tmp);
 
     // Return x-coordinate
     result.x
 }
 
+/// Performs Starknet's array hash over `elements`.
+///
+/// This folds [pedersen_hash] over the slice starting from `Fp::zero()`, then mixes in the
+/// element count: `h(h(...h(0, e0), e1)..., en-1), n)`. Equivalent to feeding each element into
+/// a [PedersenHasher] in order and calling [PedersenHasher::finalize].
+pub fn pedersen_hash_array(elements: &[Fp]) -> Fp {
+    let mut hasher = PedersenHasher::new();
+    for &e in elements {
+        hasher.update(e);
+    }
+    hasher.finalize()
+}
+
+/// Incremental builder for [pedersen_hash_array], for callers that want to hash a stream of
+/// elements without buffering them into a slice first.
+pub struct PedersenHasher {
+    state: Fp,
+    len: u64,
+}
+
+impl PedersenHasher {
+    /// Creates a new hasher with no elements folded in yet.
+    pub fn new() -> Self {
+        Self {
+            state: Fp::zero(),
+            len: 0,
+        }
+    }
+
+    /// Folds `element` into the running hash.
+    pub fn update(&mut self, element: Fp) {
+        self.state = pedersen_hash(self.state, element);
+        self.len += 1;
+    }
+
+    /// Mixes in the element count and returns the final array hash.
+    pub fn finalize(self) -> Fp {
+        pedersen_hash(self.state, Fp::from(self.len))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -260,6 +366,27 @@ mod tests {
 
             assert_eq!(two, expected);
         }
+
+        /// Same idea as `two`, but for a value that spans into the second `u64` limb.
+        /// `into_bits` has no `target_endian`-specific code path, so this holds identically on
+        /// little- and big-endian targets.
+        #[test]
+        fn crosses_limb_boundary() {
+            let two_pow_64 = {
+                let mut acc = Fp::one();
+                for _ in 0..64 {
+                    acc += acc;
+                }
+                acc
+            };
+
+            let bits = two_pow_64.into_bits();
+
+            let mut expected = BitArray::<Lsb0, [u64; 4]>::default();
+            expected.set(64, true);
+
+            assert_eq!(bits, expected);
+        }
     }
 
     mod curve {
@@ -317,6 +444,23 @@ mod tests {
             assert_eq!(g_triple, expected);
         }
 
+        #[test]
+        fn multiply_fixed_matches_multiply() {
+            let g = curve_generator();
+            let table = build_window_table(&g);
+
+            for n in [1u64, 2, 3, 15, 16, 17, 248] {
+                let mut bits = BitArray::<Lsb0, [u64; 4]>::default();
+                for i in 0..64 {
+                    bits.set(i, (n >> i) & 1 == 1);
+                }
+
+                let expected = g.multiply(&bits[..248]);
+                let actual = CurvePoint::multiply_fixed(&table, &bits[..248]);
+                assert_eq!(actual, expected, "mismatch for n={n}");
+            }
+        }
+
         #[test]
         fn p0() {
             let expected = curve_from_xy_str(
@@ -389,4 +533,41 @@ mod tests {
 
         assert_eq!(hash, expected);
     }
+
+    mod hash_array {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn empty() {
+            let expected = pedersen_hash(Fp::zero(), Fp::zero());
+            assert_eq!(pedersen_hash_array(&[]), expected);
+        }
+
+        #[test]
+        fn matches_manual_chain() {
+            let a = Fp::from(1u64);
+            let b = Fp::from(2u64);
+            let c = Fp::from(3u64);
+
+            let acc = pedersen_hash(Fp::zero(), a);
+            let acc = pedersen_hash(acc, b);
+            let acc = pedersen_hash(acc, c);
+            let expected = pedersen_hash(acc, Fp::from(3u64));
+
+            assert_eq!(pedersen_hash_array(&[a, b, c]), expected);
+        }
+
+        #[test]
+        fn hasher_matches_array() {
+            let elements = [Fp::from(1u64), Fp::from(2u64), Fp::from(3u64)];
+
+            let mut hasher = PedersenHasher::new();
+            for &e in &elements {
+                hasher.update(e);
+            }
+
+            assert_eq!(hasher.finalize(), pedersen_hash_array(&elements));
+        }
+    }
 }